@@ -1,10 +1,81 @@
 use crate::utils::dirs::app_data_dir;
 use crate::utils::string_util;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{Connection, OpenFlags};
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+// 密钥存放在系统 keychain 里，service/account 固定，首次启动时随机生成
+const KEYCHAIN_SERVICE: &str = "com.lanaya.app";
+const KEYCHAIN_ACCOUNT: &str = "sqlite-db-key";
+
+type SqlitePool = r2d2::Pool<SqliteConnectionManager>;
+
+// 整个进程共用一个连接池：剪贴板监听线程写、UI 线程读，池化 + WAL 让两者不再互相阻塞
+static POOL: OnceLock<SqlitePool> = OnceLock::new();
+// 只用来串行化 pool() 里"迁移加密 + 建池"那一段，建好之后都走上面的 POOL，不再加锁
+static POOL_INIT_LOCK: Mutex<()> = Mutex::new(());
+
+// 每写入多少条记录触发一次自动备份，达到这个次数就不等定时器了，立刻备份
+const BACKUP_WRITE_THRESHOLD: u64 = 200;
+// 定时备份的周期
+const BACKUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+static WRITE_COUNT_SINCE_BACKUP: AtomicU64 = AtomicU64::new(0);
+
+// 剪贴板内容的类型，binary 类型的数据走 blob 列，文本仍然走 content 列
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentType {
+    Text,
+    Image,
+    Rtf,
+    Html,
+}
+
+impl Default for ContentType {
+    fn default() -> Self {
+        ContentType::Text
+    }
+}
+
+impl ContentType {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::Text => "text",
+            ContentType::Image => "image",
+            ContentType::Rtf => "rtf",
+            ContentType::Html => "html",
+        }
+    }
+}
+
+impl From<&str> for ContentType {
+    fn from(s: &str) -> Self {
+        match s {
+            "image" => ContentType::Image,
+            "rtf" => ContentType::Rtf,
+            "html" => ContentType::Html,
+            _ => ContentType::Text,
+        }
+    }
+}
+
+impl rusqlite::ToSql for ContentType {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
+    }
+}
+
+impl rusqlite::types::FromSql for ContentType {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        value.as_str().map(ContentType::from)
+    }
+}
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, Default, PartialEq)]
 pub struct Record {
@@ -13,32 +84,158 @@ pub struct Record {
     pub md5: String,
     pub create_time: u64,
     pub is_favorite: bool,
+    pub data_type: ContentType,
+    // 图片等二进制内容存这里，文本类型为 None
+    pub data: Option<Vec<u8>>,
     // 仅在搜索返回时使用
     pub content_highlight: Option<String>,
 }
 
+// 搜索模式，参考 atuin 的设计：前缀匹配、全文匹配、模糊匹配（LIKE 兜底）
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchMode {
+    Prefix,
+    FullText,
+    Fuzzy,
+}
+
+// 结构化查询条件，参考 atuin 的 OptFilters 设计：按需拼接 WHERE 子句，
+// 让前端可以分页翻历史、只看收藏、或限定时间范围，而不必把整张表加载到内存。
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct RecordQuery {
+    pub before: Option<u64>,
+    pub after: Option<u64>,
+    pub favorite_only: bool,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+    pub reverse: bool,
+    pub keyword: Option<String>,
+}
+
+impl Default for RecordQuery {
+    fn default() -> Self {
+        RecordQuery {
+            before: None,
+            after: None,
+            favorite_only: false,
+            limit: None,
+            offset: None,
+            reverse: true,
+            keyword: None,
+        }
+    }
+}
+
 pub struct SqliteDB {
-    conn: Connection,
+    conn: PooledConnection<SqliteConnectionManager>,
+    // FTS5 扩展不可用时（例如某些精简编译的 sqlite），回退到 LIKE 查询
+    fts_available: bool,
 }
 
 const SQLITE_FILE: &str = "data.sqlite";
 
 #[allow(unused)]
 impl SqliteDB {
-    pub fn new() -> Self {
-        let data_dir = app_data_dir().unwrap().join(SQLITE_FILE);
-        let c = Connection::open_with_flags(data_dir, OpenFlags::SQLITE_OPEN_READ_WRITE).unwrap();
-        SqliteDB { conn: c }
+    pub fn new() -> Result<Self> {
+        let c = Self::pool()?.get()?;
+        let fts_available = Self::check_fts_available(&c);
+        Ok(SqliteDB {
+            conn: c,
+            fts_available,
+        })
     }
     pub fn add(&self) -> i64 {
         self.conn.last_insert_rowid()
     }
-    pub fn init() {
+
+    fn check_fts_available(conn: &Connection) -> bool {
+        conn.query_row("SELECT count(*) FROM record_fts", [], |row| row.get::<_, i64>(0))
+            .is_ok()
+    }
+
+    // 懒初始化的全局连接池：第一次取连接时做好加密迁移，之后每个连接复用同样的 key/PRAGMA
+    fn pool() -> Result<&'static SqlitePool> {
+        if let Some(p) = POOL.get() {
+            return Ok(p);
+        }
+        // 加密迁移 + 建池只能发生一次：剪贴板监听线程和 UI 线程都可能在首次启动时
+        // 同时跑到这里，不加锁的话两边会对同一个明文库并发跑 migrate_to_encrypted。
+        let _guard = POOL_INIT_LOCK.lock().unwrap();
+        if let Some(p) = POOL.get() {
+            return Ok(p);
+        }
+        let data_dir = app_data_dir().unwrap().join(SQLITE_FILE);
+        let key = Self::db_key()?;
+        if data_dir.exists() && Self::is_plaintext(&data_dir)? {
+            Self::migrate_to_encrypted(&data_dir, &key)?;
+        }
+        let pool = Self::build_pool(data_dir, key)?;
+        // 真正校验密钥是否正确：密钥错误时 sqlcipher 直到第一次实际访问才会报错
+        pool.get()?
+            .query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map_err(|_| anyhow!("failed to open encrypted database: wrong key"))?;
+        let _ = POOL.set(pool);
+        Ok(POOL.get().unwrap())
+    }
+
+    fn build_pool(data_dir: PathBuf, key: String) -> Result<SqlitePool> {
+        let manager = SqliteConnectionManager::file(data_dir)
+            .with_flags(OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE)
+            .with_init(move |c| {
+                c.pragma_update(None, "key", &key)?;
+                c.pragma_update(None, "journal_mode", &"WAL")?;
+                c.pragma_update(None, "synchronous", &"NORMAL")?;
+                c.busy_timeout(Duration::from_secs(5))?;
+                Ok(())
+            });
+        Ok(r2d2::Pool::builder().build(manager)?)
+    }
+
+    // 获取（或首次启动时生成并写入）系统 keychain 里保存的数据库密钥
+    fn db_key() -> Result<String> {
+        let entry = keyring::Entry::new(KEYCHAIN_SERVICE, KEYCHAIN_ACCOUNT)?;
+        match entry.get_password() {
+            Ok(key) => Ok(key),
+            Err(keyring::Error::NoEntry) => {
+                let key = string_util::md5(uuid::Uuid::new_v4().to_string().as_str());
+                entry.set_password(&key)?;
+                Ok(key)
+            }
+            Err(e) => Err(anyhow!(e)),
+        }
+    }
+
+    fn is_plaintext(data_dir: &Path) -> Result<bool> {
+        let c = Connection::open_with_flags(data_dir, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        Ok(c.query_row("SELECT count(*) FROM sqlite_master", [], |row| {
+            row.get::<_, i64>(0)
+        })
+        .is_ok())
+    }
+
+    // 通过 sqlcipher_export 把明文库整体导出成一份加密库，再原子替换旧文件
+    fn migrate_to_encrypted(data_dir: &Path, key: &str) -> Result<()> {
+        let tmp_path = data_dir.with_extension("sqlite.encrypting");
+        let c = Connection::open(data_dir)?;
+        c.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            (tmp_path.to_string_lossy().to_string(), key),
+        )?;
+        c.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        c.execute("DETACH DATABASE encrypted", ())?;
+        drop(c);
+        std::fs::rename(&tmp_path, data_dir)?;
+        Ok(())
+    }
+
+    pub fn init() -> Result<()> {
         let data_dir = app_data_dir().unwrap().join(SQLITE_FILE);
         if !Path::new(&data_dir).exists() {
-            File::create(&data_dir).unwrap();
+            File::create(&data_dir)?;
         }
-        let c = Connection::open_with_flags(data_dir, OpenFlags::SQLITE_OPEN_READ_WRITE).unwrap();
+        let c = Self::pool()?.get()?;
         let sql = r#"
         create table if not exists record
         (
@@ -46,22 +243,159 @@ impl SqliteDB {
             content     TEXT,
             md5         VARCHAR(200) DEFAULT '',
             create_time INTEGER,
-            is_favorite INTEGER DEFAULT 0
+            is_favorite INTEGER DEFAULT 0,
+            data_type   TEXT DEFAULT 'text',
+            blob        BLOB
         );
         "#;
-        c.execute(sql, ()).unwrap();
+        c.execute(sql, ())?;
+
+        // 老版本建的表没有这两列，补上即可；列已存在时 ALTER 会报错，忽略掉
+        let _ = c.execute("ALTER TABLE record ADD COLUMN data_type TEXT DEFAULT 'text'", ());
+        let _ = c.execute("ALTER TABLE record ADD COLUMN blob BLOB", ());
+
+        // 维护一张 FTS5 虚拟表用于全文检索，content/content_rowid 指回 record 表，
+        // 所以不会重复存储数据。如果当前 sqlite 没有编译 FTS5 扩展，这里会失败，
+        // 记一条警告后跳过即可，search() 会自动回退到 LIKE 查询。
+        if let Err(e) = Self::init_fts(&c) {
+            log::warn!("fts5 unavailable, falling back to LIKE search: {}", e);
+        }
+        Ok(())
+    }
+
+    fn init_fts(c: &Connection) -> Result<()> {
+        c.execute_batch(
+            r#"
+            CREATE VIRTUAL TABLE IF NOT EXISTS record_fts USING fts5(
+                content,
+                content='record',
+                content_rowid='id'
+            );
+
+            CREATE TRIGGER IF NOT EXISTS record_ai AFTER INSERT ON record BEGIN
+                INSERT INTO record_fts(rowid, content) VALUES (new.id, new.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS record_ad AFTER DELETE ON record BEGIN
+                INSERT INTO record_fts(record_fts, rowid, content) VALUES('delete', old.id, old.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS record_au AFTER UPDATE ON record BEGIN
+                INSERT INTO record_fts(record_fts, rowid, content) VALUES('delete', old.id, old.content);
+                INSERT INTO record_fts(rowid, content) VALUES (new.id, new.content);
+            END;
+            "#,
+        )?;
+
+        // 首次创建时回填已有数据，之后的写入都由上面的触发器维护
+        let backfilled: i64 = c.query_row("SELECT count(*) FROM record_fts", [], |row| row.get(0))?;
+        let total: i64 = c.query_row("SELECT count(*) FROM record", [], |row| row.get(0))?;
+        if backfilled == 0 && total > 0 {
+            c.execute(
+                "INSERT INTO record_fts(rowid, content) SELECT id, content FROM record",
+                (),
+            )?;
+        }
+        Ok(())
+    }
+
+    // 文本按 content 算 md5，二进制内容（图片等）按原始字节算，避免同图不同文件名被判定为不同记录。
+    // string_util 目前只有 md5(&str)，没有按字节算的版本，这里直接算，不绕 string_util。
+    fn record_md5(r: &Record) -> String {
+        match r.data_type {
+            ContentType::Text => string_util::md5(r.content.as_str()),
+            _ => format!("{:x}", md5::compute(r.data.as_deref().unwrap_or(&[]))),
+        }
     }
 
     pub fn insert_record(&self, r: Record) -> Result<i64> {
-        let sql = "insert into record (content,md5,create_time,is_favorite) values (?1,?2,?3,?4)";
-        let md5 = string_util::md5(r.content.as_str());
+        let md5 = Self::record_md5(&r);
         let now = chrono::Local::now().timestamp_millis() as u64;
-        let res = self
-            .conn
-            .execute(sql, (&r.content, md5, now, &r.is_favorite))?;
+        match r.data_type {
+            ContentType::Text => {
+                let sql = "insert into record (content,md5,create_time,is_favorite,data_type) values (?1,?2,?3,?4,?5)";
+                self.conn.execute(
+                    sql,
+                    (&r.content, md5, now, &r.is_favorite, r.data_type),
+                )?;
+            }
+            _ => {
+                let data = r.data.as_deref().unwrap_or(&[]);
+                let sql = "insert into record (content,md5,create_time,is_favorite,data_type,blob) values (?1,?2,?3,?4,?5,zeroblob(?6))";
+                self.conn.execute(
+                    sql,
+                    (&r.content, md5, now, &r.is_favorite, r.data_type, data.len() as i64),
+                )?;
+                let row_id = self.conn.last_insert_rowid();
+                self.write_blob(row_id, data)?;
+            }
+        }
+        self.note_write();
         Ok(self.conn.last_insert_rowid())
     }
 
+    // 每次读写 blob 的分片大小
+    const BLOB_CHUNK_SIZE: usize = 64 * 1024;
+
+    // 用 rusqlite 的 Blob 接口分片写入，不把整段数据作为一个绑定参数交给 INSERT
+    // 语句（那样 rusqlite/sqlite 还要在内部再拷贝一份）。注意：`data` 本身在调用
+    // 这里之前已经是完整的 `Vec<u8>`（来自 `Record::data`），所以这只是省掉了语句
+    // 绑定那一层的整份拷贝，并不是端到端的流式写入——调用方仍需要把整张图片先读进内存。
+    fn write_blob(&self, row_id: i64, data: &[u8]) -> Result<()> {
+        use std::io::Write;
+        let mut blob =
+            self.conn
+                .blob_open(rusqlite::DatabaseName::Main, "record", "blob", row_id, false)?;
+        for chunk in data.chunks(Self::BLOB_CHUNK_SIZE) {
+            blob.write_all(chunk)?;
+        }
+        Ok(())
+    }
+
+    // 同上，读取时按固定大小分片调用 Blob::read，而不是一次性 read_to_end。
+    // 返回值仍然是整份 `Vec<u8>`（`Record::data` 的类型决定的），所以内存峰值
+    // 没有本质变化，只是避免了 read_to_end 内部反复扩容拷贝缓冲区。
+    fn read_blob(&self, row_id: i64) -> Result<Vec<u8>> {
+        use std::io::Read;
+        let mut blob =
+            self.conn
+                .blob_open(rusqlite::DatabaseName::Main, "record", "blob", row_id, true)?;
+        let mut buf = vec![0u8; blob.size() as usize];
+        let mut read_total = 0;
+        let mut chunk = [0u8; Self::BLOB_CHUNK_SIZE];
+        loop {
+            let n = blob.read(&mut chunk)?;
+            if n == 0 {
+                break;
+            }
+            buf[read_total..read_total + n].copy_from_slice(&chunk[..n]);
+            read_total += n;
+        }
+        Ok(buf)
+    }
+
+    // 非文本记录的 content 列只存占位描述，真正的数据要单独从 blob 列读出来再填回去
+    fn hydrate(&self, mut r: Record) -> Result<Record> {
+        if r.data_type != ContentType::Text {
+            r.data = Some(self.read_blob(r.id as i64)?);
+        }
+        Ok(r)
+    }
+
+    // 记一次写入，攒够 BACKUP_WRITE_THRESHOLD 次就在后台触发一次备份，
+    // 不等定时调度器。备份本身跑在独立线程里，不阻塞当前写入。
+    fn note_write(&self) {
+        let count = WRITE_COUNT_SINCE_BACKUP.fetch_add(1, Ordering::Relaxed) + 1;
+        if count >= BACKUP_WRITE_THRESHOLD {
+            WRITE_COUNT_SINCE_BACKUP.store(0, Ordering::Relaxed);
+            std::thread::spawn(|| {
+                if let Err(e) = Self::backup_now() {
+                    log::warn!("write-triggered backup failed: {}", e);
+                }
+            });
+        }
+    }
+
     fn find_record_by_md5(&self, md5: String) -> Result<Record> {
         let sql = "SELECT id, content, md5, create_time, is_favorite FROM record WHERE md5 = ?1";
         let r = self.conn.query_row(sql, [md5], |row| {
@@ -79,11 +413,12 @@ impl SqliteDB {
         // 获取当前毫秒级时间戳
         let now = chrono::Local::now().timestamp_millis() as u64;
         self.conn.execute(sql, [&r.id, &now])?;
+        self.note_write();
         Ok(())
     }
 
     pub fn insert_if_not_exist(&self, r: Record) -> Result<()> {
-        let md5 = string_util::md5(r.content.as_str());
+        let md5 = Self::record_md5(&r);
         match self.find_record_by_md5(md5) {
             Ok(res) => {
                 self.update_record_create_time(res)?;
@@ -110,9 +445,63 @@ impl SqliteDB {
     }
 
     pub fn find_all(&self) -> Result<Vec<Record>> {
-        let sql = "SELECT id, content, md5, create_time, is_favorite FROM record order by create_time desc";
-        let mut stmt = self.conn.prepare(sql)?;
-        let mut rows = stmt.query([])?;
+        self.query(RecordQuery::default())
+    }
+
+    pub fn find_by_key(&self, key: String, limit: u64) -> Result<Vec<Record>> {
+        self.query(RecordQuery {
+            keyword: Some(key),
+            limit: Some(limit),
+            ..Default::default()
+        })
+    }
+
+    // 按条件动态拼接 SQL（参数化，非字符串拼接），支持时间范围、是否收藏、
+    // 分页和排序方向，用于前端翻历史记录。
+    pub fn query(&self, q: RecordQuery) -> Result<Vec<Record>> {
+        let mut sql =
+            "SELECT id, content, md5, create_time, is_favorite, data_type FROM record".to_string();
+        let mut conditions = vec![];
+        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![];
+
+        if let Some(before) = q.before {
+            conditions.push("create_time < ?".to_string());
+            params.push(Box::new(before));
+        }
+        if let Some(after) = q.after {
+            conditions.push("create_time > ?".to_string());
+            params.push(Box::new(after));
+        }
+        if q.favorite_only {
+            conditions.push("is_favorite = 1".to_string());
+        }
+        if let Some(keyword) = &q.keyword {
+            conditions.push("content like ?".to_string());
+            params.push(Box::new(format!("%{}%", keyword)));
+        }
+        if !conditions.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&conditions.join(" AND "));
+        }
+        sql.push_str(if q.reverse {
+            " ORDER BY create_time DESC"
+        } else {
+            " ORDER BY create_time ASC"
+        });
+        if let Some(limit) = q.limit {
+            sql.push_str(" LIMIT ?");
+            params.push(Box::new(limit));
+        } else if q.offset.is_some() {
+            // sqlite 要求 OFFSET 必须搭配 LIMIT，没有显式 limit 时用 -1 表示不限制
+            sql.push_str(" LIMIT -1");
+        }
+        if let Some(offset) = q.offset {
+            sql.push_str(" OFFSET ?");
+            params.push(Box::new(offset));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
         let mut res = vec![];
         while let Some(row) = rows.next()? {
             let r = Record {
@@ -121,15 +510,81 @@ impl SqliteDB {
                 md5: row.get(2)?,
                 create_time: row.get(3)?,
                 is_favorite: row.get(4)?,
+                data_type: row.get(5)?,
+                data: None,
                 content_highlight: None,
             };
-            res.push(r);
+            res.push(self.hydrate(r)?);
         }
         Ok(res)
     }
 
-    pub fn find_by_key(&self, key: String, limit: u64) -> Result<Vec<Record>> {
-        let sql = "SELECT id, content, md5, create_time, is_favorite FROM record where content like ?1 order by create_time desc limit ?2";
+    // 按指定模式搜索：Prefix/FullText 走 FTS5，Fuzzy 走原来的 LIKE 查询。
+    // 如果 FTS5 扩展不可用，任何模式都会自动回退到 Fuzzy。
+    pub fn search(&self, key: String, mode: SearchMode, limit: u64) -> Result<Vec<Record>> {
+        let mode = if self.fts_available {
+            mode
+        } else {
+            SearchMode::Fuzzy
+        };
+        // 空白关键字拼出来的 MATCH '' 在 FTS5 里是语法错误，而不是"什么都不匹配"，
+        // 直接当空结果处理。注意 Fuzzy（LIKE '%%'）在空关键字下会匹配所有记录，两者行为不同。
+        if matches!(mode, SearchMode::Prefix | SearchMode::FullText) && key.trim().is_empty() {
+            return Ok(vec![]);
+        }
+        match mode {
+            SearchMode::Prefix => {
+                self.search_fts(format!("\"{}\"*", Self::escape_fts_token(&key)), limit)
+            }
+            SearchMode::FullText => {
+                let query = key
+                    .split_whitespace()
+                    .map(|w| format!("\"{}\"", Self::escape_fts_token(w)))
+                    .collect::<Vec<String>>()
+                    .join(" AND ");
+                self.search_fts(query, limit)
+            }
+            SearchMode::Fuzzy => self.search_like(key, limit),
+        }
+    }
+
+    // FTS5 字符串字面量里的 " 需要写成 "" 来转义，否则用户搜索里带引号的内容
+    // （比如刚复制的一段代码）会拼出非法的 MATCH 表达式
+    fn escape_fts_token(s: &str) -> String {
+        s.replace('"', "\"\"")
+    }
+
+    fn search_fts(&self, match_query: String, limit: u64) -> Result<Vec<Record>> {
+        let sql = r#"
+        SELECT record.id, record.content, record.md5, record.create_time, record.is_favorite,
+               record.data_type, highlight(record_fts, 0, '<mark>', '</mark>')
+        FROM record_fts
+        JOIN record ON record.id = record_fts.rowid
+        WHERE record_fts MATCH ?1
+        ORDER BY bm25(record_fts)
+        LIMIT ?2
+        "#;
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query((&match_query, limit))?;
+        let mut res = vec![];
+        while let Some(row) = rows.next()? {
+            let r = Record {
+                id: row.get(0)?,
+                content: row.get(1)?,
+                md5: row.get(2)?,
+                create_time: row.get(3)?,
+                is_favorite: row.get(4)?,
+                data_type: row.get(5)?,
+                data: None,
+                content_highlight: row.get(6)?,
+            };
+            res.push(self.hydrate(r)?);
+        }
+        Ok(res)
+    }
+
+    fn search_like(&self, key: String, limit: u64) -> Result<Vec<Record>> {
+        let sql = "SELECT id, content, md5, create_time, is_favorite, data_type FROM record where content like ?1 order by create_time desc limit ?2";
         let mut stmt = self.conn.prepare(sql)?;
         let mut rows = stmt.query([format!("%{}%", key), limit.to_string()])?;
         let mut res = vec![];
@@ -142,9 +597,11 @@ impl SqliteDB {
                 md5: row.get(2)?,
                 create_time: row.get(3)?,
                 is_favorite: row.get(4)?,
-                content_highlight: None,
+                data_type: row.get(5)?,
+                data: None,
+                content_highlight,
             };
-            res.push(r);
+            res.push(self.hydrate(r)?);
         }
         Ok(res)
     }
@@ -156,7 +613,7 @@ impl SqliteDB {
             .collect::<Vec<String>>()
             .join(",");
         let sql = format!(
-            "SELECT id, content, md5, create_time, is_favorite FROM record where id in ({})",
+            "SELECT id, content, md5, create_time, is_favorite, data_type FROM record where id in ({})",
             ids_string
         );
         let mut stmt = self.conn.prepare(sql.as_str())?;
@@ -169,9 +626,11 @@ impl SqliteDB {
                 md5: row.get(2)?,
                 create_time: row.get(3)?,
                 is_favorite: row.get(4)?,
+                data_type: row.get(5)?,
+                data: None,
                 content_highlight: None,
             };
-            res.push(r);
+            res.push(self.hydrate(r)?);
         }
         Ok(res)
     }
@@ -188,27 +647,170 @@ impl SqliteDB {
         self.conn.execute(sql, [&limit])?;
         Ok(())
     }
+
+    // 在线备份：用 SQLite Online Backup API 把当前库整体拷贝到 dest，
+    // 拷贝过程中不阻塞其它连接的读写。
+    // 目标库必须用同一把密钥 key 过之后才能开始 backup：sqlcipher 的在线备份
+    // 是按页拷贝的，目标连接没 key 的话拷过去的就是明文页，等于白加密。
+    pub fn backup_to(&self, dest: &Path) -> Result<()> {
+        let key = Self::db_key()?;
+        let mut dst = Connection::open(dest)?;
+        dst.pragma_update(None, "key", &key)?;
+        let backup = rusqlite::backup::Backup::new(&self.conn, &mut dst)?;
+        backup.run_to_completion(100, Duration::from_millis(50), None)?;
+        Ok(())
+    }
+
+    // 截断 WAL 文件，避免长时间运行后 wal 无限增长
+    pub fn checkpoint(&self) -> Result<()> {
+        self.conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        Ok(())
+    }
+
+    fn backup_dir() -> Result<PathBuf> {
+        let dir = app_data_dir().unwrap().join("backups");
+        std::fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    // 立即做一次 checkpoint + 备份，备份文件名里带时间戳，方便按时间恢复
+    pub fn backup_now() -> Result<()> {
+        let db = Self::new()?;
+        db.checkpoint()?;
+        let dest = Self::backup_dir()?.join(format!(
+            "data-{}.sqlite",
+            chrono::Local::now().format("%Y%m%d%H%M%S")
+        ));
+        db.backup_to(&dest)
+    }
+
+    // 后台定时备份：每隔 BACKUP_INTERVAL 触发一次，和 note_write() 的按写入数触发互为补充
+    pub fn spawn_backup_scheduler() {
+        std::thread::spawn(|| loop {
+            std::thread::sleep(BACKUP_INTERVAL);
+            WRITE_COUNT_SINCE_BACKUP.store(0, Ordering::Relaxed);
+            if let Err(e) = Self::backup_now() {
+                log::warn!("scheduled backup failed: {}", e);
+            }
+        });
+    }
 }
 
 #[test]
 fn test_sqlite_insert() {
-    SqliteDB::init();
+    SqliteDB::init().unwrap();
     let r = Record {
         content: "123456".to_string(),
         md5: "e10adc3949ba59abbe56e057f20f883e".to_string(),
         create_time: 1234568,
         ..Default::default()
     };
-    assert_eq!(SqliteDB::new().insert_record(r).unwrap(), 1_i64)
+    assert_eq!(SqliteDB::new().unwrap().insert_record(r).unwrap(), 1_i64)
 }
 
 #[test]
 fn test_find_by_md5() {
-    // SqliteDB::init();
-    // let a = SqliteDB::new().find_all().unwrap();
+    // SqliteDB::init().unwrap();
+    // let a = SqliteDB::new().unwrap().find_all().unwrap();
 
     // println!("{:?}", a);
 
-    let b = SqliteDB::new().find_by_key("r".to_string(), 10).unwrap();
+    let b = SqliteDB::new()
+        .unwrap()
+        .find_by_key("r".to_string(), 10)
+        .unwrap();
     println!("{:?}", b);
 }
+
+#[test]
+fn test_pool_connection_uses_wal() {
+    let db = SqliteDB::new().unwrap();
+    let mode: String = db
+        .conn
+        .pragma_query_value(None, "journal_mode", |row| row.get(0))
+        .unwrap();
+    assert_eq!(mode.to_lowercase(), "wal");
+}
+
+#[test]
+fn test_insert_and_find_binary_record() {
+    SqliteDB::init().unwrap();
+    let data = vec![1u8, 2, 3, 4, 5, 250, 251, 252];
+    let r = Record {
+        content: "[image]".to_string(),
+        data_type: ContentType::Image,
+        data: Some(data.clone()),
+        ..Default::default()
+    };
+    let db = SqliteDB::new().unwrap();
+    let id = db.insert_record(r).unwrap();
+    let found = db.find_by_id_in(vec![id as u64]).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].data_type, ContentType::Image);
+    assert_eq!(found[0].data.as_deref(), Some(data.as_slice()));
+}
+
+#[test]
+fn test_migrate_plaintext_to_encrypted() {
+    let path = std::env::temp_dir().join(format!(
+        "lanaya_test_plaintext_{}.sqlite",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+    {
+        let c = Connection::open(&path).unwrap();
+        c.execute_batch(
+            "create table record (
+                id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+                content TEXT,
+                md5 VARCHAR(200) DEFAULT '',
+                create_time INTEGER,
+                is_favorite INTEGER DEFAULT 0,
+                data_type TEXT DEFAULT 'text',
+                blob BLOB
+            );
+            insert into record (content, md5, create_time) values ('hello', 'md5-hello', 1);",
+        )
+        .unwrap();
+    }
+    assert!(SqliteDB::is_plaintext(&path).unwrap());
+
+    let key = "test-migration-key";
+    SqliteDB::migrate_to_encrypted(&path, key).unwrap();
+    assert!(!SqliteDB::is_plaintext(&path).unwrap());
+
+    let c = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_WRITE).unwrap();
+    c.pragma_update(None, "key", &key).unwrap();
+    let content: String = c
+        .query_row("select content from record where id = 1", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(content, "hello");
+
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn test_backup_to_produces_an_encrypted_copy() {
+    SqliteDB::init().unwrap();
+    let db = SqliteDB::new().unwrap();
+    db.checkpoint().unwrap();
+
+    let dest = std::env::temp_dir().join(format!("lanaya_test_backup_{}.sqlite", std::process::id()));
+    let _ = std::fs::remove_file(&dest);
+    db.backup_to(&dest).unwrap();
+
+    // 没有 key 直接打开应该读不出 schema：证明备份文件是加密的，不是明文拷贝
+    assert!(!SqliteDB::is_plaintext(&dest).unwrap());
+
+    // 用同一把 key 能正常打开并读到数据，证明备份确实可用
+    let key = SqliteDB::db_key().unwrap();
+    let c = Connection::open_with_flags(&dest, OpenFlags::SQLITE_OPEN_READ_WRITE).unwrap();
+    c.pragma_update(None, "key", &key).unwrap();
+    c.query_row("select count(*) from record", [], |row| row.get::<_, i64>(0))
+        .unwrap();
+
+    let _ = std::fs::remove_file(&dest);
+}
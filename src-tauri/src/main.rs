@@ -3,6 +3,10 @@
     windows_subsystem = "windows"
 )]
 
+mod core;
+mod utils;
+
+use crate::core::database::SqliteDB;
 use tauri::SystemTray;
 use tauri::{App, CustomMenuItem, Manager, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem};
 use tauri_plugin_sql::TauriSql;
@@ -11,11 +15,14 @@ fn main() {
     // here `"quit".to_string()` defines the menu item id, and the second parameter is the menu item label.
     let show = CustomMenuItem::new("show".to_string(), "唤起主界面(Cmd+Shift+C)");
     let hide = CustomMenuItem::new("hide".to_string(), "隐藏窗口(Esc)");
+    let backup = CustomMenuItem::new("backup".to_string(), "立即备份历史记录");
     let quit = CustomMenuItem::new("quit".to_string(), "退出");
     let tray_menu = SystemTrayMenu::new()
         .add_item(show)
         .add_item(hide)
         .add_native_item(SystemTrayMenuItem::Separator)
+        .add_item(backup)
+        .add_native_item(SystemTrayMenuItem::Separator)
         .add_item(quit);
     let system_tray = SystemTray::new().with_menu(tray_menu);
 
@@ -24,7 +31,7 @@ fn main() {
             set_up(app);
             Ok(())
         })
-        // .invoke_handler(tauri::generate_handler![greet])
+        .invoke_handler(tauri::generate_handler![backup_history_now])
         .plugin(TauriSql::default())
         .system_tray(system_tray)
         .on_system_tray_event(|app, event| match event {
@@ -38,6 +45,11 @@ fn main() {
                     let window = app.get_window("main").unwrap();
                     window.hide().unwrap();
                 }
+                "backup" => {
+                    if let Err(e) = SqliteDB::backup_now() {
+                        log::warn!("manual backup failed: {}", e);
+                    }
+                }
                 "quit" => {
                     app.exit(0);
                 }
@@ -49,7 +61,14 @@ fn main() {
         .expect("error while running tauri application");
 }
 
+// 暴露给前端调用的备份命令，和托盘菜单的“立即备份”走同一个实现
+#[tauri::command]
+fn backup_history_now() -> Result<(), String> {
+    SqliteDB::backup_now().map_err(|e| e.to_string())
+}
+
 fn set_up(app: &mut App) {
     // Make the docker NOT to have an active app when started
     app.set_activation_policy(tauri::ActivationPolicy::Accessory);
+    SqliteDB::spawn_backup_scheduler();
 }